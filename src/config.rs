@@ -0,0 +1,166 @@
+use std::fmt;
+use std::io;
+
+/// Server settings that used to be hardcoded constants in `main`, now
+/// sourced from an optional config file so deployments don't require
+/// recompiling.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    pub db_path: String,
+    pub reset_on_start: bool,
+    pub pool_size: u32,
+    pub max_body_size: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: "127.0.0.1:8080".to_string(),
+            db_path: "songs.db".to_string(),
+            reset_on_start: true,
+            pool_size: 8,
+            max_body_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    InvalidValue { key: String, value: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to read config file: {}", e),
+            ConfigError::InvalidValue { key, value } => {
+                write!(f, "invalid value \"{}\" for config key \"{}\"", value, key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+/// Reads and parses a simple `key = value` config file, tolerating
+/// `[section]` headers and `#`-prefixed comments. A missing file yields the
+/// defaults; a malformed or out-of-range value yields a `ConfigError`
+/// describing exactly which key was bad.
+pub fn load(path: &str) -> Result<ServerConfig, ConfigError> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse(&contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(ServerConfig::default()),
+        Err(e) => Err(ConfigError::from(e)),
+    }
+}
+
+fn parse(contents: &str) -> Result<ServerConfig, ConfigError> {
+    let mut config = ServerConfig::default();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').ok_or_else(|| ConfigError::InvalidValue {
+            key: line.to_string(),
+            value: String::new(),
+        })?;
+        let key = key.trim();
+        let value = value.trim();
+
+        let invalid = || ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+
+        match key {
+            "port" => {
+                let port: u16 = value.parse().map_err(|_| invalid())?;
+                config.bind_address = format!("127.0.0.1:{}", port);
+            }
+            "bind_address" => {
+                value.parse::<std::net::SocketAddr>().map_err(|_| invalid())?;
+                config.bind_address = value.to_string();
+            }
+            "db_path" => config.db_path = value.to_string(),
+            "reset_on_start" => config.reset_on_start = value.parse().map_err(|_| invalid())?,
+            "pool_size" => {
+                let pool_size: u32 = value.parse().map_err(|_| invalid())?;
+                if pool_size == 0 {
+                    return Err(invalid());
+                }
+                config.pool_size = pool_size;
+            }
+            "max_body_size" => {
+                let max_body_size: usize = value.parse().map_err(|_| invalid())?;
+                if max_body_size == 0 {
+                    return Err(invalid());
+                }
+                config.max_body_size = max_body_size;
+            }
+            _ => {} // unknown keys are ignored for forward-compatibility
+        }
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_file_yields_defaults() {
+        let config = load("/nonexistent/path/to/server.conf").unwrap();
+        assert_eq!(config.bind_address, ServerConfig::default().bind_address);
+        assert_eq!(config.pool_size, ServerConfig::default().pool_size);
+    }
+
+    #[test]
+    fn parses_overridden_values() {
+        let config = parse("port = 9090\ndb_path = test.db\npool_size = 4\n").unwrap();
+        assert_eq!(config.bind_address, "127.0.0.1:9090");
+        assert_eq!(config.db_path, "test.db");
+        assert_eq!(config.pool_size, 4);
+    }
+
+    #[test]
+    fn ignores_comments_and_section_headers() {
+        let config = parse("[server]\n# a comment\nport = 9090\n").unwrap();
+        assert_eq!(config.bind_address, "127.0.0.1:9090");
+    }
+
+    #[test]
+    fn malformed_numeric_value_is_invalid() {
+        let err = parse("pool_size = not-a-number").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "pool_size"));
+    }
+
+    #[test]
+    fn zero_pool_size_is_invalid() {
+        let err = parse("pool_size = 0").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "pool_size"));
+    }
+
+    #[test]
+    fn zero_max_body_size_is_invalid() {
+        let err = parse("max_body_size = 0").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "max_body_size"));
+    }
+
+    #[test]
+    fn malformed_bind_address_is_invalid() {
+        let err = parse("bind_address = not-an-address").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { key, .. } if key == "bind_address"));
+    }
+}
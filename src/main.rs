@@ -1,10 +1,19 @@
+mod config;
+
+use config::ServerConfig;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::net::{TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
+// Per-request checkout from a shared pool, replacing the old Mutex<Connection>.
+type DbPool = Pool<SqliteConnectionManager>;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Song {
     id: u32,
@@ -21,22 +30,296 @@ struct NewSong {
     genre: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Playlist {
+    id: u32,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct NewPlaylist {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AppendSong {
+    song_id: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct User {
+    id: u32,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct NewUser {
+    name: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct UserPlayCount {
+    user: String,
+    count: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct SongStatus {
+    song: Song,
+    plays: Vec<UserPlayCount>,
+}
+
+// Tagged envelope every JSON endpoint serializes into: Success -> 200,
+// Failure -> 4xx, Fatal -> 500.
+#[derive(Serialize)]
+#[serde(tag = "type", content = "content")]
+enum ApiResponse<T> {
+    Success(T),
+    Failure(String),
+    Fatal(String),
+}
+
+fn status_reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+fn send_response<T: Serialize>(stream: &mut TcpStream, status: u16, response: &ApiResponse<T>) {
+    let body = serde_json::to_string(response)
+        .unwrap_or_else(|_| "{\"type\":\"Fatal\",\"content\":\"serialization error\"}".to_string());
+    let http_response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\n\r\n{}",
+        status,
+        status_reason(status),
+        body
+    );
+    if let Err(e) = stream.write_all(http_response.as_bytes()) {
+        eprintln!("Failed to write response to client: {}", e);
+    }
+}
+
+// Sends a Fatal/500 and returns None if the pool can't hand out a connection.
+fn checkout(pool: &DbPool, stream: &mut TcpStream) -> Option<r2d2::PooledConnection<SqliteConnectionManager>> {
+    match pool.get() {
+        Ok(conn) => Some(conn),
+        Err(e) => {
+            send_response::<()>(stream, 500, &ApiResponse::Fatal(format!("Failed to check out a database connection: {}", e)));
+            None
+        }
+    }
+}
+
+// Extracts the request-target (e.g. `/playlists/3/skip`) from the request line.
+fn request_path(request: &str) -> &str {
+    request.split_whitespace().nth(1).unwrap_or("")
+}
+
+enum RequestReadError {
+    Io(std::io::Error),
+    TooLarge,
+    MissingHeaderTerminator,
+}
+
+// Grows `buffer` as it reads instead of truncating at a fixed size: first
+// until "\r\n\r\n" is seen, then until Content-Length bytes of body arrive.
+fn read_full_request(stream: &mut TcpStream, max_body_size: usize) -> Result<Vec<u8>, RequestReadError> {
+    const READ_CHUNK_SIZE: usize = 1024;
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; READ_CHUNK_SIZE];
+
+    let headers_end = loop {
+        if let Some(pos) = buffer
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+        {
+            break pos;
+        }
+        // Cap the header search too, or a client that never sends the
+        // terminator grows this buffer without bound.
+        if buffer.len() >= max_body_size {
+            return Err(RequestReadError::TooLarge);
+        }
+        let read = stream.read(&mut chunk).map_err(RequestReadError::Io)?;
+        if read == 0 {
+            return Err(RequestReadError::MissingHeaderTerminator);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let headers = String::from_utf8_lossy(&buffer[..headers_end]).into_owned();
+    let content_length = parse_content_length(&headers).unwrap_or(0);
+    if content_length > max_body_size {
+        return Err(RequestReadError::TooLarge);
+    }
+
+    let body_start = headers_end + 4;
+    let body_end = body_start + content_length;
+    while buffer.len() < body_end {
+        let read = stream.read(&mut chunk).map_err(RequestReadError::Io)?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    Ok(buffer)
+}
+
+fn parse_content_length(headers: &str) -> Option<usize> {
+    headers
+        .lines()
+        .find(|line| line.starts_with("Content-Length:"))
+        .and_then(|line| line.split(": ").nth(1))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+}
+
+fn exact_body<'a>(body: &'a str, content_length: usize, stream: &mut TcpStream) -> Option<&'a str> {
+    match body.get(..content_length) {
+        Some(body) => Some(body),
+        None => {
+            send_response::<()>(
+                stream,
+                400,
+                &ApiResponse::Failure("Request body shorter than declared Content-Length.".to_string()),
+            );
+            None
+        }
+    }
+}
+
+fn header_value<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers
+        .lines()
+        .find(|line| {
+            line.split_once(':')
+                .map(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+                .unwrap_or(false)
+        })
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, value)| value.trim())
+}
+
+fn playlist_exists(db_connection: &Connection, playlist_id: u32) -> bool {
+    db_connection
+        .query_row(
+            "SELECT 1 FROM playlists WHERE id = ?1",
+            params![playlist_id],
+            |_| Ok(()),
+        )
+        .is_ok()
+}
+
+fn song_exists(db_connection: &Connection, song_id: u32) -> bool {
+    db_connection
+        .query_row("SELECT 1 FROM songs WHERE id = ?1", params![song_id], |_| Ok(()))
+        .is_ok()
+}
+
+fn fetch_playlist_songs(db_connection: &Connection, playlist_id: u32) -> Result<Vec<Song>> {
+    let mut prepared_statement = db_connection.prepare(
+        "SELECT songs.id, songs.title, songs.artist, songs.genre, songs.play_count
+         FROM playlist_songs
+         JOIN songs ON songs.id = playlist_songs.song_id
+         WHERE playlist_songs.playlist_id = ?1
+         ORDER BY playlist_songs.position ASC",
+    )?;
+
+    let songs = prepared_statement
+        .query_map(params![playlist_id], |row| {
+            Ok(Song {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                artist: row.get(2)?,
+                genre: row.get(3)?,
+                play_count: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<Song>>>()?;
+
+    Ok(songs)
+}
+
+fn user_play_counts(db_connection: &Connection, user_id: u32) -> Result<HashMap<u32, u32>> {
+    let mut prepared_statement = db_connection
+        .prepare("SELECT song_id, COUNT(*) FROM plays WHERE user_id = ?1 GROUP BY song_id")?;
+
+    let counts = prepared_statement
+        .query_map(params![user_id], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?))
+        })?
+        .collect::<Result<HashMap<u32, u32>>>()?;
+
+    Ok(counts)
+}
+
+// Ranks common songs by combined (a + b) play count, ties broken by id ascending.
+fn rank_intersection(a_counts: &HashMap<u32, u32>, b_counts: &HashMap<u32, u32>) -> Vec<(u32, u32)> {
+    let mut scored: Vec<(u32, u32)> = a_counts
+        .iter()
+        .filter_map(|(song_id, a_count)| {
+            b_counts
+                .get(song_id)
+                .map(|b_count| (*song_id, a_count + b_count))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    scored
+}
+
+// Groups rows (already ordered by song id) into one SongStatus per song;
+// a song with no attributed plays gets plays: [] rather than being dropped.
+fn aggregate_song_statuses(rows: Vec<(Song, Option<UserPlayCount>)>) -> Vec<SongStatus> {
+    let mut statuses: Vec<SongStatus> = Vec::new();
+    for (song, user_play_count) in rows {
+        match statuses.last_mut() {
+            Some(last) if last.song.id == song.id => last.plays.extend(user_play_count),
+            _ => statuses.push(SongStatus {
+                song,
+                plays: user_play_count.into_iter().collect(),
+            }),
+        }
+    }
+    statuses
+}
+
 fn main() -> Result<()> {
-    // Initialize SQLite database and reset it
-    let db_connection = Arc::new(Mutex::new(init_and_reset_database()?));
+    let config = match config::load("server.conf") {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid server configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Initialize the connection pool and, if configured, reset the database
+    let pool = init_and_reset_database(&config)?;
     let visit_count = Arc::new(Mutex::new(0));
-    // Bind the server to localhost:8080
-    let listener = TcpListener::bind("127.0.0.1:8080").expect("Failed to bind to port 8080");
-    println!("The server is currently listening on localhost:8080.");
+    // Bind the server to the configured address
+    let listener = TcpListener::bind(&config.bind_address)
+        .unwrap_or_else(|_| panic!("Failed to bind to {}", config.bind_address));
+    println!(
+        "The server is currently listening on {}.",
+        config.bind_address
+    );
 
     // Handle incoming connections
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
                 let visit_count = Arc::clone(&visit_count);
-                let db_connection = Arc::clone(&db_connection);
+                let pool = pool.clone();
+                let max_body_size = config.max_body_size;
                 thread::spawn(move || {
-                    handle_client(stream, visit_count, db_connection);
+                    handle_client(stream, visit_count, pool, max_body_size);
                 });
             }
             Err(e) => eprintln!("Failed to accept connection: {}", e),
@@ -46,8 +329,21 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn init_and_reset_database() -> Result<Connection> {
-    let db_connection = Connection::open("songs.db")?;
+fn init_and_reset_database(config: &ServerConfig) -> Result<DbPool> {
+    // Give each pooled connection a busy timeout and WAL mode, so genuine
+    // write contention between pooled connections waits and retries like it
+    // did under the old mutex instead of immediately surfacing "database is
+    // locked" as a 500.
+    let manager = SqliteConnectionManager::file(&config.db_path).with_init(|conn| {
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+        Ok(())
+    });
+    let pool = Pool::builder()
+        .max_size(config.pool_size)
+        .build(manager)
+        .expect("Failed to build the SQLite connection pool");
+    let db_connection = pool.get().expect("Failed to check out a connection");
 
     // Create the songs' table
     db_connection.execute(
@@ -61,69 +357,127 @@ fn init_and_reset_database() -> Result<Connection> {
         [],
     )?;
 
-    // Reset the database
-    db_connection.execute("DELETE FROM songs", [])?;
-    // Reset the counter
-    db_connection.execute("DELETE FROM sqlite_sequence WHERE name = 'songs'", [])?;
-    Ok(db_connection)
-}
+    // Create the playlists table
+    db_connection.execute(
+        "CREATE TABLE IF NOT EXISTS playlists (
+            id                INTEGER PRIMARY KEY AUTOINCREMENT,
+            name              TEXT NOT NULL,
+            current_position  INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Create the playlist_songs join table, ordered by an explicit position
+    // column so the playlist behaves like a queue rather than an unordered set.
+    db_connection.execute(
+        "CREATE TABLE IF NOT EXISTS playlist_songs (
+            playlist_id  INTEGER NOT NULL,
+            song_id      INTEGER NOT NULL,
+            position     INTEGER NOT NULL,
+            FOREIGN KEY(playlist_id) REFERENCES playlists(id),
+            FOREIGN KEY(song_id) REFERENCES songs(id)
+        )",
+        [],
+    )?;
+
+    // Create the users table
+    db_connection.execute(
+        "CREATE TABLE IF NOT EXISTS users (
+            id    INTEGER PRIMARY KEY AUTOINCREMENT,
+            name  TEXT NOT NULL
+        )",
+        [],
+    )?;
 
-fn handle_client(
-    mut stream: TcpStream,
-    visit_count: Arc<Mutex<u32>>,
-    db_connection: Arc<Mutex<Connection>>,
-) {
-    let mut buffer = [0; 1024];
-    if let Err(e) = stream.read(&mut buffer) {
-        println!("Failed to read from client: {}", e);
-        return;
+    // Create the plays table, recording which user triggered each play
+    db_connection.execute(
+        "CREATE TABLE IF NOT EXISTS plays (
+            id         INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id    INTEGER NOT NULL,
+            song_id    INTEGER NOT NULL,
+            played_at  TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id),
+            FOREIGN KEY(song_id) REFERENCES songs(id)
+        )",
+        [],
+    )?;
+
+    // Reset the database, unless the deployment opted out of the
+    // destructive wipe-on-startup behavior
+    if config.reset_on_start {
+        db_connection.execute("DELETE FROM songs", [])?;
+        db_connection.execute("DELETE FROM playlist_songs", [])?;
+        db_connection.execute("DELETE FROM playlists", [])?;
+        db_connection.execute("DELETE FROM plays", [])?;
+        db_connection.execute("DELETE FROM users", [])?;
+        // Reset the counters
+        db_connection.execute("DELETE FROM sqlite_sequence WHERE name = 'songs'", [])?;
+        db_connection.execute("DELETE FROM sqlite_sequence WHERE name = 'playlists'", [])?;
+        db_connection.execute("DELETE FROM sqlite_sequence WHERE name = 'users'", [])?;
     }
+    drop(db_connection);
+    Ok(pool)
+}
+
+fn handle_client(mut stream: TcpStream, visit_count: Arc<Mutex<u32>>, pool: DbPool, max_body_size: usize) {
+    let buffer = match read_full_request(&mut stream, max_body_size) {
+        Ok(buffer) => buffer,
+        Err(RequestReadError::TooLarge) => {
+            send_response::<()>(&mut stream, 413, &ApiResponse::Failure("Request body too large.".to_string()));
+            return;
+        }
+        Err(RequestReadError::MissingHeaderTerminator) => {
+            send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Malformed request: missing header terminator.".to_string()));
+            return;
+        }
+        Err(RequestReadError::Io(e)) => {
+            println!("Failed to read from client: {}", e);
+            return;
+        }
+    };
 
     let request = String::from_utf8_lossy(&buffer);
 
-    // Extract header and body
-    let headers_end = request.find("\r\n\r\n").unwrap_or(request.len());
+    // Extract header and body. read_full_request only returns a buffer once
+    // it has seen "\r\n\r\n", so find() here is guaranteed to succeed.
+    let headers_end = match request.find("\r\n\r\n") {
+        Some(pos) => pos,
+        None => return,
+    };
     let headers = &request[..headers_end];
     let body = &request[headers_end + 4..];
 
     // Check for content type
     let is_json_request = headers.contains("Content-Type: application/json");
+    // Identify the caller for play attribution, if they sent one
+    let user_id = header_value(headers, "X-User-Id").and_then(|value| value.parse::<u32>().ok());
 
     if request.starts_with("GET /count ") {
         let mut count = visit_count.lock().unwrap();
         *count += 1;
 
-        let response = format!(
-            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nVisit count: {}",
-            count
-        );
-
-        if let Err(e) = stream.write_all(response.as_bytes()) {
-            println!("Failed to write response to client: {}", e);
-        }
+        send_response(&mut stream, 200, &ApiResponse::Success(format!("Visit count: {}", count)));
     } else if request.starts_with("POST /songs/new ") {
         // Handle new song creation
         if is_json_request {
-            let content_length_header = headers
-                .lines()
-                .find(|line| line.starts_with("Content-Length:"))
-                .and_then(|line| line.split(": ").nth(1))
-                .and_then(|value| value.trim().parse::<usize>().ok());
-
-            if let Some(content_length) = content_length_header {
-                // Get the exact body
-                let body = &body[..content_length];
+            if let Some(content_length) = parse_content_length(headers) {
+                let body = match exact_body(body, content_length, &mut stream) {
+                    Some(body) => body,
+                    None => return,
+                };
 
                 match serde_json::from_str::<NewSong>(body) {
                     Ok(new_song) => {
-                        let db_connection = db_connection.lock().unwrap();
+                        let db_connection = match checkout(&pool, &mut stream) {
+                            Some(conn) => conn,
+                            None => return,
+                        };
                         if let Err(e) = db_connection.execute(
                             "INSERT INTO songs (title, artist, genre, play_count) VALUES (?1, ?2, ?3, ?4)",
                             params![new_song.title, new_song.artist, new_song.genre, 0],
                         ) {
                             println!("Failed to insert song: {}", e);
-                            let response = "HTTP/1.1 500 Internal Server Error\r\n\r\n";
-                            let _ = stream.write_all(response.as_bytes());
+                            send_response::<()>(&mut stream, 500, &ApiResponse::Fatal(format!("Failed to insert song: {}", e)));
                             return;
                         }
 
@@ -137,26 +491,18 @@ fn handle_client(
                             play_count: 0,
                         };
 
-                        let response_body = serde_json::to_string(&song).unwrap();
-                        let response = format!(
-                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
-                            response_body
-                        );
-                        let _ = stream.write_all(response.as_bytes());
+                        send_response(&mut stream, 200, &ApiResponse::Success(song));
                     }
                     Err(e) => {
                         println!("Failed to parse JSON: {}", e);
-                        let response = "HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain\r\n\r\nInvalid JSON format.";
-                        let _ = stream.write_all(response.as_bytes());
+                        send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Invalid JSON format.".to_string()));
                     }
                 }
             } else {
-                let response = "HTTP/1.1 411 Length Required\r\nContent-Type: text/plain\r\n\r\nMissing Content-Length header.";
-                let _ = stream.write_all(response.as_bytes());
+                send_response::<()>(&mut stream, 411, &ApiResponse::Failure("Missing Content-Length header.".to_string()));
             }
         } else {
-            let response = "HTTP/1.1 415 Unsupported Media Type\r\nContent-Type: text/plain\r\n\r\nExpected Content-Type: application/json.";
-            let _ = stream.write_all(response.as_bytes());
+            send_response::<()>(&mut stream, 415, &ApiResponse::Failure("Expected Content-Type: application/json.".to_string()));
         }
     } else if request.starts_with("GET /songs/search?") {
         // Song search functionality
@@ -209,8 +555,17 @@ fn handle_client(
             .iter()
             .map(|s| s as &dyn rusqlite::ToSql)
             .collect();
-        let db_connection = db_connection.lock().unwrap();
-        let mut prepared_statement = db_connection.prepare(&final_query).unwrap();
+        let db_connection = match checkout(&pool, &mut stream) {
+            Some(conn) => conn,
+            None => return,
+        };
+        let mut prepared_statement = match db_connection.prepare(&final_query) {
+            Ok(statement) => statement,
+            Err(e) => {
+                send_response::<()>(&mut stream, 500, &ApiResponse::Fatal(format!("Failed to prepare query: {}", e)));
+                return;
+            }
+        };
 
         let song_iter = prepared_statement.query_map(sql_params.as_slice(), |row| {
             Ok(Song {
@@ -225,18 +580,11 @@ fn handle_client(
         match song_iter {
             Ok(results) => {
                 let songs: Vec<Song> = results.filter_map(Result::ok).collect();
-                let response_body = serde_json::to_string(&songs).unwrap();
-                let response = format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
-                    response_body
-                );
-                let _ = stream.write_all(response.as_bytes());
+                send_response(&mut stream, 200, &ApiResponse::Success(songs));
             }
             Err(e) => {
                 eprintln!("Failed to query songs: {}", e);
-                let response =
-                    "HTTP/1.1 500 Internal Server Error\r\nContent-Type: text/plain\r\n\r\n";
-                let _ = stream.write_all(response.as_bytes());
+                send_response::<()>(&mut stream, 500, &ApiResponse::Fatal(format!("Failed to query songs: {}", e)));
             }
         }
     } else if request.starts_with("GET /songs/play/") {
@@ -249,47 +597,439 @@ fn handle_client(
             .next()
             .unwrap_or("");
         if let Ok(id) = id_str.parse::<u32>() {
-            let db_connection = db_connection.lock().unwrap();
-            let mut prepared_statement = db_connection
-                .prepare("SELECT id, title, artist, genre, play_count FROM songs WHERE id = ?1")
-                .unwrap();
-            let song: Result<Song> = prepared_statement.query_row([id], |row| {
-                Ok(Song {
+            let db_connection = match checkout(&pool, &mut stream) {
+                Some(conn) => conn,
+                None => return,
+            };
+            // UPDATE ... WHERE id instead of read-then-write avoids losing
+            // increments under concurrent plays of the same song.
+            let rows_affected = match db_connection.execute(
+                "UPDATE songs SET play_count = play_count + 1 WHERE id = ?1",
+                params![id],
+            ) {
+                Ok(rows_affected) => rows_affected,
+                Err(e) => {
+                    send_response::<()>(&mut stream, 500, &ApiResponse::Fatal(format!("Failed to update play count: {}", e)));
+                    return;
+                }
+            };
+
+            if rows_affected == 0 {
+                send_response::<()>(&mut stream, 404, &ApiResponse::Failure("Song not found".to_string()));
+            } else {
+                let song: Result<Song> = db_connection.query_row(
+                    "SELECT id, title, artist, genre, play_count FROM songs WHERE id = ?1",
+                    [id],
+                    |row| {
+                        Ok(Song {
+                            id: row.get(0)?,
+                            title: row.get(1)?,
+                            artist: row.get(2)?,
+                            genre: row.get(3)?,
+                            play_count: row.get(4)?,
+                        })
+                    },
+                );
+
+                match song {
+                    Ok(song) => {
+                        if let Some(user_id) = user_id {
+                            if let Err(e) = db_connection.execute(
+                                "INSERT INTO plays (user_id, song_id) VALUES (?1, ?2)",
+                                params![user_id, song.id],
+                            ) {
+                                println!("Failed to record play attribution: {}", e);
+                            }
+                        }
+
+                        send_response(&mut stream, 200, &ApiResponse::Success(song));
+                    }
+                    Err(_) => {
+                        send_response::<()>(&mut stream, 404, &ApiResponse::Failure("Song not found".to_string()));
+                    }
+                }
+            }
+        } else {
+            send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Invalid song ID.".to_string()));
+        }
+    } else if request.starts_with("POST /playlists/new ") {
+        // Create a new, empty playlist
+        if is_json_request {
+            if let Some(content_length) = parse_content_length(headers) {
+                let body = match exact_body(body, content_length, &mut stream) {
+                    Some(body) => body,
+                    None => return,
+                };
+
+                match serde_json::from_str::<NewPlaylist>(body) {
+                    Ok(new_playlist) => {
+                        let db_connection = match checkout(&pool, &mut stream) {
+                            Some(conn) => conn,
+                            None => return,
+                        };
+                        if let Err(e) = db_connection.execute(
+                            "INSERT INTO playlists (name) VALUES (?1)",
+                            params![new_playlist.name],
+                        ) {
+                            send_response::<()>(
+                                &mut stream,
+                                500,
+                                &ApiResponse::Fatal(format!("Failed to insert playlist: {}", e)),
+                            );
+                            return;
+                        }
+
+                        let playlist_id = db_connection.last_insert_rowid() as u32;
+                        let playlist = Playlist {
+                            id: playlist_id,
+                            name: new_playlist.name,
+                        };
+                        send_response(&mut stream, 200, &ApiResponse::Success(playlist));
+                    }
+                    Err(_) => {
+                        send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Invalid JSON format.".to_string()));
+                    }
+                }
+            } else {
+                send_response::<()>(&mut stream, 411, &ApiResponse::Failure("Missing Content-Length header.".to_string()));
+            }
+        } else {
+            send_response::<()>(&mut stream, 415, &ApiResponse::Failure("Expected Content-Type: application/json.".to_string()));
+        }
+    } else if request.starts_with("POST /playlists/") && request_path(&request).ends_with("/append") {
+        // Append a song to the end of a playlist's queue
+        let id_str = request_path(&request)
+            .trim_start_matches("/playlists/")
+            .trim_end_matches("/append");
+
+        if let Ok(playlist_id) = id_str.parse::<u32>() {
+            if is_json_request {
+                if let Some(content_length) = parse_content_length(headers) {
+                    let body = match exact_body(body, content_length, &mut stream) {
+                        Some(body) => body,
+                        None => return,
+                    };
+
+                    match serde_json::from_str::<AppendSong>(body) {
+                        Ok(append) => {
+                            let db_connection = match checkout(&pool, &mut stream) {
+                                Some(conn) => conn,
+                                None => return,
+                            };
+                            if !playlist_exists(&db_connection, playlist_id) {
+                                send_response::<()>(&mut stream, 404, &ApiResponse::Failure("Playlist not found".to_string()));
+                                return;
+                            }
+                            if !song_exists(&db_connection, append.song_id) {
+                                send_response::<()>(&mut stream, 404, &ApiResponse::Failure("Song not found".to_string()));
+                                return;
+                            }
+
+                            // MAX(position) is computed in the same INSERT, not read back
+                            // first, so two appends to the same playlist can't collide on
+                            // the same slot.
+                            if let Err(e) = db_connection.execute(
+                                "INSERT INTO playlist_songs (playlist_id, song_id, position)
+                                 SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0)
+                                 FROM playlist_songs WHERE playlist_id = ?1",
+                                params![playlist_id, append.song_id],
+                            ) {
+                                send_response::<()>(
+                                    &mut stream,
+                                    500,
+                                    &ApiResponse::Fatal(format!("Failed to append song: {}", e)),
+                                );
+                                return;
+                            }
+
+                            match fetch_playlist_songs(&db_connection, playlist_id) {
+                                Ok(songs) => send_response(&mut stream, 200, &ApiResponse::Success(songs)),
+                                Err(e) => send_response::<()>(
+                                    &mut stream,
+                                    500,
+                                    &ApiResponse::Fatal(format!("Failed to fetch playlist: {}", e)),
+                                ),
+                            }
+                        }
+                        Err(_) => {
+                            send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Invalid JSON format.".to_string()));
+                        }
+                    }
+                } else {
+                    send_response::<()>(&mut stream, 411, &ApiResponse::Failure("Missing Content-Length header.".to_string()));
+                }
+            } else {
+                send_response::<()>(&mut stream, 415, &ApiResponse::Failure("Expected Content-Type: application/json.".to_string()));
+            }
+        } else {
+            send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Invalid playlist ID.".to_string()));
+        }
+    } else if request.starts_with("POST /playlists/") && request_path(&request).ends_with("/skip") {
+        // Return the song at the playlist's current head, then advance past it
+        let id_str = request_path(&request)
+            .trim_start_matches("/playlists/")
+            .trim_end_matches("/skip");
+
+        if let Ok(playlist_id) = id_str.parse::<u32>() {
+            let mut db_connection = match checkout(&pool, &mut stream) {
+                Some(conn) => conn,
+                None => return,
+            };
+            if !playlist_exists(&db_connection, playlist_id) {
+                send_response::<()>(&mut stream, 404, &ApiResponse::Failure("Playlist not found".to_string()));
+                return;
+            }
+
+            // Read + advance need a transaction, unlike play_count/append above,
+            // since the head lookup and the position bump are different
+            // statements over different tables (playlists, playlist_songs).
+            let transaction = match db_connection.transaction() {
+                Ok(transaction) => transaction,
+                Err(e) => {
+                    send_response::<()>(&mut stream, 500, &ApiResponse::Fatal(format!("Failed to start transaction: {}", e)));
+                    return;
+                }
+            };
+
+            let current_song: Result<Song> = transaction.query_row(
+                "SELECT songs.id, songs.title, songs.artist, songs.genre, songs.play_count
+                 FROM playlists
+                 JOIN playlist_songs
+                   ON playlist_songs.playlist_id = playlists.id
+                  AND playlist_songs.position = playlists.current_position
+                 JOIN songs ON songs.id = playlist_songs.song_id
+                 WHERE playlists.id = ?1",
+                params![playlist_id],
+                |row| {
+                    Ok(Song {
+                        id: row.get(0)?,
+                        title: row.get(1)?,
+                        artist: row.get(2)?,
+                        genre: row.get(3)?,
+                        play_count: row.get(4)?,
+                    })
+                },
+            );
+
+            match current_song {
+                Ok(song) => {
+                    if let Err(e) = transaction.execute(
+                        "UPDATE playlists SET current_position = current_position + 1 WHERE id = ?1",
+                        params![playlist_id],
+                    ) {
+                        send_response::<()>(
+                            &mut stream,
+                            500,
+                            &ApiResponse::Fatal(format!("Failed to advance playlist: {}", e)),
+                        );
+                        return;
+                    }
+                    if let Err(e) = transaction.commit() {
+                        send_response::<()>(
+                            &mut stream,
+                            500,
+                            &ApiResponse::Fatal(format!("Failed to commit playlist advance: {}", e)),
+                        );
+                        return;
+                    }
+                    send_response(&mut stream, 200, &ApiResponse::Success(song));
+                }
+                Err(_) => {
+                    // Transaction drops here without a commit, rolling back the no-op read.
+                    send_response::<()>(&mut stream, 404, &ApiResponse::Failure("No more songs in playlist".to_string()));
+                }
+            }
+        } else {
+            send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Invalid playlist ID.".to_string()));
+        }
+    } else if request.starts_with("GET /playlists/") {
+        // Return the songs in a playlist, in queue order
+        let id_str = request_path(&request).trim_start_matches("/playlists/");
+
+        if let Ok(playlist_id) = id_str.parse::<u32>() {
+            let db_connection = match checkout(&pool, &mut stream) {
+                Some(conn) => conn,
+                None => return,
+            };
+            if !playlist_exists(&db_connection, playlist_id) {
+                send_response::<()>(&mut stream, 404, &ApiResponse::Failure("Playlist not found".to_string()));
+                return;
+            }
+            match fetch_playlist_songs(&db_connection, playlist_id) {
+                Ok(songs) => send_response(&mut stream, 200, &ApiResponse::Success(songs)),
+                Err(e) => send_response::<()>(
+                    &mut stream,
+                    500,
+                    &ApiResponse::Fatal(format!("Failed to fetch playlist: {}", e)),
+                ),
+            }
+        } else {
+            send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Invalid playlist ID.".to_string()));
+        }
+    } else if request.starts_with("POST /users/new ") {
+        // Register a new user
+        if is_json_request {
+            if let Some(content_length) = parse_content_length(headers) {
+                let body = match exact_body(body, content_length, &mut stream) {
+                    Some(body) => body,
+                    None => return,
+                };
+
+                match serde_json::from_str::<NewUser>(body) {
+                    Ok(new_user) => {
+                        let db_connection = match checkout(&pool, &mut stream) {
+                            Some(conn) => conn,
+                            None => return,
+                        };
+                        if let Err(e) = db_connection
+                            .execute("INSERT INTO users (name) VALUES (?1)", params![new_user.name])
+                        {
+                            send_response::<()>(
+                                &mut stream,
+                                500,
+                                &ApiResponse::Fatal(format!("Failed to insert user: {}", e)),
+                            );
+                            return;
+                        }
+
+                        let user_id = db_connection.last_insert_rowid() as u32;
+                        let user = User {
+                            id: user_id,
+                            name: new_user.name,
+                        };
+                        send_response(&mut stream, 200, &ApiResponse::Success(user));
+                    }
+                    Err(_) => {
+                        send_response::<()>(&mut stream, 400, &ApiResponse::Failure("Invalid JSON format.".to_string()));
+                    }
+                }
+            } else {
+                send_response::<()>(&mut stream, 411, &ApiResponse::Failure("Missing Content-Length header.".to_string()));
+            }
+        } else {
+            send_response::<()>(&mut stream, 415, &ApiResponse::Failure("Expected Content-Type: application/json.".to_string()));
+        }
+    } else if request.starts_with("GET /status ") {
+        // Attribute each song's plays to the users who triggered them
+        let db_connection = match checkout(&pool, &mut stream) {
+            Some(conn) => conn,
+            None => return,
+        };
+        // LEFT JOIN so a song with zero plays, or plays that never carried an
+        // X-User-Id, still appears in the summary with an empty breakdown
+        // instead of being dropped from the response entirely.
+        let mut prepared_statement = match db_connection.prepare(
+            "SELECT songs.id, songs.title, songs.artist, songs.genre, songs.play_count,
+                    users.name, COUNT(plays.id)
+             FROM songs
+             LEFT JOIN plays ON plays.song_id = songs.id
+             LEFT JOIN users ON users.id = plays.user_id
+             GROUP BY songs.id, users.id
+             ORDER BY songs.id ASC",
+        ) {
+            Ok(statement) => statement,
+            Err(e) => {
+                send_response::<()>(&mut stream, 500, &ApiResponse::Fatal(format!("Failed to prepare query: {}", e)));
+                return;
+            }
+        };
+
+        let row_iter = prepared_statement.query_map([], |row| {
+            let user: Option<String> = row.get(5)?;
+            let count: u32 = row.get(6)?;
+            Ok((
+                Song {
                     id: row.get(0)?,
                     title: row.get(1)?,
                     artist: row.get(2)?,
                     genre: row.get(3)?,
                     play_count: row.get(4)?,
-                })
-            });
-
-            match song {
-                Ok(mut song) => {
-                    song.play_count += 1;
-                    db_connection
-                        .execute(
-                            "UPDATE songs SET play_count = ?1 WHERE id = ?2",
-                            params![song.play_count, song.id],
-                        )
-                        .unwrap();
-
-                    let response_body = serde_json::to_string(&song).unwrap();
-                    let response = format!(
-                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
-                        response_body
-                    );
-
-                    let _ = stream.write_all(response.as_bytes());
-                }
-                Err(_) => {
-                    let response = "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n\r\n{\"error\":\"Song not found\"}";
-                    let _ = stream.write_all(response.as_bytes());
-                }
+                },
+                user.map(|user| UserPlayCount { user, count }),
+            ))
+        });
+
+        let rows: Vec<(Song, Option<UserPlayCount>)> = match row_iter {
+            Ok(rows) => rows.filter_map(Result::ok).collect(),
+            Err(_) => Vec::new(),
+        };
+        let statuses = aggregate_song_statuses(rows);
+
+        send_response(&mut stream, 200, &ApiResponse::Success(statuses));
+    } else if request.starts_with("GET /recommend/intersect?") {
+        // Songs both users have played, ranked by combined engagement
+        let query = request
+            .split_once("GET /recommend/intersect?")
+            .unwrap()
+            .1
+            .split(" ")
+            .next()
+            .unwrap_or("");
+
+        let params: HashMap<String, String> = query
+            .split('&')
+            .filter_map(|pair| {
+                let mut parts = pair.split('=');
+                Some((parts.next()?.to_lowercase(), parts.next()?.to_string()))
+            })
+            .collect();
+
+        let user_a = params.get("user_a").and_then(|v| v.parse::<u32>().ok());
+        let user_b = params.get("user_b").and_then(|v| v.parse::<u32>().ok());
+
+        match (user_a, user_b) {
+            (Some(user_a), Some(user_b)) => {
+                let db_connection = match checkout(&pool, &mut stream) {
+                    Some(conn) => conn,
+                    None => return,
+                };
+                let a_counts = match user_play_counts(&db_connection, user_a) {
+                    Ok(counts) => counts,
+                    Err(e) => {
+                        send_response::<()>(&mut stream, 500, &ApiResponse::Fatal(format!("Failed to fetch play counts: {}", e)));
+                        return;
+                    }
+                };
+                let b_counts = match user_play_counts(&db_connection, user_b) {
+                    Ok(counts) => counts,
+                    Err(e) => {
+                        send_response::<()>(&mut stream, 500, &ApiResponse::Fatal(format!("Failed to fetch play counts: {}", e)));
+                        return;
+                    }
+                };
+
+                let scored = rank_intersection(&a_counts, &b_counts);
+
+                let songs: Vec<Song> = scored
+                    .into_iter()
+                    .filter_map(|(song_id, _score)| {
+                        db_connection
+                            .query_row(
+                                "SELECT id, title, artist, genre, play_count FROM songs WHERE id = ?1",
+                                params![song_id],
+                                |row| {
+                                    Ok(Song {
+                                        id: row.get(0)?,
+                                        title: row.get(1)?,
+                                        artist: row.get(2)?,
+                                        genre: row.get(3)?,
+                                        play_count: row.get(4)?,
+                                    })
+                                },
+                            )
+                            .ok()
+                    })
+                    .collect();
+
+                send_response(&mut stream, 200, &ApiResponse::Success(songs));
+            }
+            _ => {
+                send_response::<()>(
+                    &mut stream,
+                    400,
+                    &ApiResponse::Failure("Missing or invalid user_a/user_b parameters.".to_string()),
+                );
             }
-        } else {
-            let response =
-                "HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain\r\n\r\nInvalid song ID.";
-            let _ = stream.write_all(response.as_bytes());
         }
     } else {
         let response = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nWelcome to the Rust-powered web server!";
@@ -298,3 +1038,241 @@ fn handle_client(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(id: u32, title: &str) -> Song {
+        Song {
+            id,
+            title: title.to_string(),
+            artist: "artist".to_string(),
+            genre: "genre".to_string(),
+            play_count: 0,
+        }
+    }
+
+    #[test]
+    fn aggregate_song_statuses_groups_plays_by_song() {
+        let rows = vec![
+            (
+                song(1, "A"),
+                Some(UserPlayCount { user: "alice".to_string(), count: 2 }),
+            ),
+            (
+                song(1, "A"),
+                Some(UserPlayCount { user: "bob".to_string(), count: 1 }),
+            ),
+            (
+                song(2, "B"),
+                Some(UserPlayCount { user: "alice".to_string(), count: 3 }),
+            ),
+        ];
+
+        let statuses = aggregate_song_statuses(rows);
+
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].song.id, 1);
+        assert_eq!(statuses[0].plays.len(), 2);
+        assert_eq!(statuses[1].song.id, 2);
+        assert_eq!(statuses[1].plays.len(), 1);
+    }
+
+    #[test]
+    fn aggregate_song_statuses_keeps_unattributed_song_with_empty_plays() {
+        // A song with no rows in `plays` (LEFT JOIN yields
+        // a single (song, None) row) should still appear, just with no breakdown.
+        let rows = vec![(song(1, "A"), None)];
+
+        let statuses = aggregate_song_statuses(rows);
+
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].song.id, 1);
+        assert!(statuses[0].plays.is_empty());
+    }
+
+    #[test]
+    fn aggregate_song_statuses_empty_input_yields_empty_output() {
+        assert!(aggregate_song_statuses(Vec::new()).is_empty());
+    }
+
+    fn playlist_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE songs (
+                id INTEGER PRIMARY KEY, title TEXT, artist TEXT, genre TEXT, play_count INTEGER
+             );
+             CREATE TABLE playlists (
+                id INTEGER PRIMARY KEY, name TEXT, current_position INTEGER NOT NULL DEFAULT 0
+             );
+             CREATE TABLE playlist_songs (
+                playlist_id INTEGER, song_id INTEGER, position INTEGER
+             );",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn fetch_playlist_songs_returns_queue_in_position_order() {
+        let conn = playlist_test_db();
+        conn.execute("INSERT INTO playlists (id, name) VALUES (1, 'p')", []).unwrap();
+        conn.execute(
+            "INSERT INTO songs (id, title, artist, genre, play_count) VALUES (1, 'first', 'a', 'g', 0), (2, 'second', 'a', 'g', 0)",
+            [],
+        )
+        .unwrap();
+        // Insert out of queue order to make sure ORDER BY position, not id, wins
+        conn.execute("INSERT INTO playlist_songs (playlist_id, song_id, position) VALUES (1, 2, 1)", []).unwrap();
+        conn.execute("INSERT INTO playlist_songs (playlist_id, song_id, position) VALUES (1, 1, 0)", []).unwrap();
+
+        let songs = fetch_playlist_songs(&conn, 1).unwrap();
+
+        assert_eq!(songs.len(), 2);
+        assert_eq!(songs[0].title, "first");
+        assert_eq!(songs[1].title, "second");
+    }
+
+    #[test]
+    fn fetch_playlist_songs_empty_playlist_yields_empty_vec() {
+        let conn = playlist_test_db();
+        conn.execute("INSERT INTO playlists (id, name) VALUES (1, 'p')", []).unwrap();
+
+        assert!(fetch_playlist_songs(&conn, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn playlist_exists_reflects_presence_in_the_table() {
+        let conn = playlist_test_db();
+        conn.execute("INSERT INTO playlists (id, name) VALUES (1, 'p')", []).unwrap();
+
+        assert!(playlist_exists(&conn, 1));
+        assert!(!playlist_exists(&conn, 2));
+    }
+
+    #[test]
+    fn song_exists_reflects_presence_in_the_table() {
+        let conn = playlist_test_db();
+        conn.execute(
+            "INSERT INTO songs (id, title, artist, genre, play_count) VALUES (1, 'first', 'a', 'g', 0)",
+            [],
+        )
+        .unwrap();
+
+        assert!(song_exists(&conn, 1));
+        assert!(!song_exists(&conn, 2));
+    }
+
+    #[test]
+    fn user_play_counts_counts_plays_per_song() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE plays (user_id INTEGER, song_id INTEGER);
+             INSERT INTO plays (user_id, song_id) VALUES (1, 10), (1, 10), (1, 20), (2, 10);",
+        )
+        .unwrap();
+
+        let counts = user_play_counts(&conn, 1).unwrap();
+
+        assert_eq!(counts.get(&10), Some(&2));
+        assert_eq!(counts.get(&20), Some(&1));
+        assert_eq!(counts.get(&30), None);
+    }
+
+    #[test]
+    fn rank_intersection_keeps_only_common_songs_ranked_by_combined_score() {
+        let a_counts = HashMap::from([(1, 3), (2, 1), (3, 5)]);
+        let b_counts = HashMap::from([(1, 2), (3, 1)]);
+
+        let ranked = rank_intersection(&a_counts, &b_counts);
+
+        // song 2 isn't in b_counts, so it's excluded; song 3 (5+1=6) outranks song 1 (3+2=5)
+        assert_eq!(ranked, vec![(3, 6), (1, 5)]);
+    }
+
+    #[test]
+    fn rank_intersection_ties_break_on_ascending_song_id() {
+        let a_counts = HashMap::from([(2, 1), (1, 1)]);
+        let b_counts = HashMap::from([(2, 1), (1, 1)]);
+
+        let ranked = rank_intersection(&a_counts, &b_counts);
+
+        assert_eq!(ranked, vec![(1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn parse_content_length_reads_the_declared_value() {
+        let headers = "POST /songs/new HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 42";
+        assert_eq!(parse_content_length(headers), Some(42));
+    }
+
+    #[test]
+    fn parse_content_length_missing_header_is_none() {
+        let headers = "GET /count HTTP/1.1\r\nHost: localhost";
+        assert_eq!(parse_content_length(headers), None);
+    }
+
+    // Writes `request` from a client thread, runs read_full_request on the server side.
+    fn read_request_over_loopback(request: &[u8], max_body_size: usize) -> Result<Vec<u8>, RequestReadError> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let request = request.to_vec();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(&request).unwrap();
+            // Let the server observe EOF on short bodies instead of blocking forever.
+            stream.shutdown(std::net::Shutdown::Write).ok();
+            // Keep the socket alive long enough for the server to finish reading.
+            thread::sleep(std::time::Duration::from_millis(50));
+        });
+        let (mut server_stream, _) = listener.accept().unwrap();
+        let result = read_full_request(&mut server_stream, max_body_size);
+        client.join().unwrap();
+        result
+    }
+
+    #[test]
+    fn read_full_request_reads_body_matching_content_length() {
+        let request = b"POST /songs/new HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+        let buffer = read_request_over_loopback(request, 1024).ok().unwrap();
+        assert!(String::from_utf8_lossy(&buffer).ends_with("hello"));
+    }
+
+    #[test]
+    fn read_full_request_handles_empty_body() {
+        let request = b"GET /count HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let buffer = read_request_over_loopback(request, 1024).ok().unwrap();
+        assert!(String::from_utf8_lossy(&buffer).ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn read_full_request_stops_at_eof_when_body_is_shorter_than_declared() {
+        // Client declares 100 bytes but only sends 5 before closing the socket.
+        let request = b"POST /songs/new HTTP/1.1\r\nContent-Length: 100\r\n\r\nhello";
+        let buffer = read_request_over_loopback(request, 1024).ok().unwrap();
+        assert!(String::from_utf8_lossy(&buffer).ends_with("hello"));
+    }
+
+    #[test]
+    fn read_full_request_rejects_body_over_max_body_size() {
+        let request = b"POST /songs/new HTTP/1.1\r\nContent-Length: 1024\r\n\r\n";
+        let result = read_request_over_loopback(request, 100);
+        assert!(matches!(result, Err(RequestReadError::TooLarge)));
+    }
+
+    #[test]
+    fn read_full_request_rejects_connection_closed_before_any_bytes() {
+        // A health check or port scan that connects and disconnects immediately.
+        let result = read_request_over_loopback(b"", 1024);
+        assert!(matches!(result, Err(RequestReadError::MissingHeaderTerminator)));
+    }
+
+    #[test]
+    fn read_full_request_bounds_header_search_by_max_body_size() {
+        // Headers that never reach "\r\n\r\n" shouldn't grow the buffer past the limit.
+        let request = b"GET /count HTTP/1.1\r\nX-Junk: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let result = read_request_over_loopback(request, 16);
+        assert!(matches!(result, Err(RequestReadError::TooLarge)));
+    }
+}